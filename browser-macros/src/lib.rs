@@ -0,0 +1,70 @@
+//! Compile-time expansion for the markup DSL, `markup!{ ... }`.
+//!
+//! This reuses the same tokenize/parse grammar as the runtime compiler in
+//! `browser`, but runs it during macro expansion so invalid markup becomes a
+//! `compile_error!` instead of a runtime error. Embedded `{ expr }` blocks
+//! are spliced into a generated `format!(...)` call so they evaluate as real
+//! Rust expressions rather than raw text.
+
+use browser::markup::markup_text_to_format;
+use proc_macro::TokenStream;
+use quote::quote_spanned;
+
+#[proc_macro]
+pub fn markup(input: TokenStream) -> TokenStream {
+    let source = input.to_string();
+
+    match markup_text_to_format(&source) {
+        Ok((html, exprs)) => {
+            let args: Result<Vec<proc_macro2::TokenStream>, _> =
+                exprs.iter().map(|expr| expr.parse()).collect();
+
+            match args {
+                Ok(args) => quote_spanned! {
+                    proc_macro2::Span::call_site() => format!(#html, #(#args),*)
+                }
+                .into(),
+                Err(_) => {
+                    quote_spanned! { proc_macro2::Span::call_site() =>
+                        compile_error!("invalid Rust expression inside a markup! block")
+                    }
+                    .into()
+                }
+            }
+        }
+        Err(err) => {
+            let message = err.to_string();
+            let span = nearest_span(&input, &message);
+
+            quote_spanned! { span => compile_error!(#message) }.into()
+        }
+    }
+}
+
+/// Best-effort recovery of the token implicated by `message`, so the
+/// `compile_error!` points at the offending node rather than at the whole
+/// macro invocation. The runtime parser only reports line/column positions
+/// against the re-stringified source, so this falls back to matching
+/// identifiers instead of threading spans through tokenization.
+fn nearest_span(input: &TokenStream, message: &str) -> proc_macro2::Span {
+    fn walk(stream: proc_macro2::TokenStream, message: &str) -> Option<proc_macro2::Span> {
+        for token in stream {
+            match &token {
+                proc_macro2::TokenTree::Ident(ident) if message.contains(&ident.to_string()) => {
+                    return Some(ident.span());
+                }
+                proc_macro2::TokenTree::Group(group) => {
+                    if let Some(span) = walk(group.stream(), message) {
+                        return Some(span);
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        None
+    }
+
+    walk(proc_macro2::TokenStream::from(input.clone()), message)
+        .unwrap_or_else(proc_macro2::Span::call_site)
+}
@@ -0,0 +1,27 @@
+#[test]
+fn expands_static_markup_into_html() {
+    let html = browser_macros::markup!(div { p "Hello" });
+
+    assert_eq!(html, "<div>\n\t<p>\n\t\tHello\n\t</p>\n</div>");
+}
+
+#[test]
+fn splices_expression_blocks_as_real_rust_expressions() {
+    let name = "World";
+    let html = browser_macros::markup!(p { { name } });
+
+    assert_eq!(html, format!("<p>\n\t{name}\n</p>"));
+}
+
+#[test]
+fn escapes_literal_braces_so_they_survive_the_format_call() {
+    let html = browser_macros::markup!(p "curly {brace} here");
+
+    assert_eq!(html, "<p>\n\tcurly {brace} here\n</p>");
+}
+
+#[test]
+fn rejects_templates_that_fail_schema_validation() {
+    let t = trybuild::TestCases::new();
+    t.compile_fail("tests/ui/missing_head.rs");
+}
@@ -0,0 +1,3 @@
+fn main() {
+    let _html = browser_macros::markup!(html { body { p "hi" } });
+}
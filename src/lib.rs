@@ -0,0 +1,3 @@
+mod markdown;
+pub mod markup;
+pub mod schema;
@@ -1,22 +1,22 @@
-mod markup;
-
-use markup::markup_text_to_html;
+use browser::markup::markup_text_to_html;
 
 fn main() {
-    println!(
-        "{}",
-        markup_text_to_html(
-            "div (class=\"flex justify-center\") {
-                p \"Hello\"
-                br
-                strong {
-                    p {
-                        \"Nice\"
-                        i \"markup\"
-                        \"bro\"
-                    }
+    let result = markup_text_to_html(
+        "div (class=\"flex justify-center\") {
+            p \"Hello\"
+            br
+            strong {
+                p {
+                    \"Nice\"
+                    i \"markup\"
+                    \"bro\"
                 }
-            }"
-        )
+            }
+        }",
     );
+
+    match result {
+        Ok(html) => println!("{html}"),
+        Err(err) => eprintln!("{err}"),
+    }
 }
@@ -0,0 +1,343 @@
+//! Compiles a block of Markdown text into real `TreeNode`s, for use by the
+//! `md "..."` marker in [`crate::markup`]. This is a small, two-pass
+//! CommonMark-style scanner rather than a full implementation: a block pass
+//! splits the source into headings, fenced code, lists and paragraphs, and an
+//! inline pass turns the text of each into emphasis, strong, code spans and
+//! links.
+
+use std::collections::HashMap;
+
+use crate::markup::{AttributeValue, Child, Span, TreeNode};
+
+enum Block {
+    Heading(u8, String),
+    Code(String),
+    List { ordered: bool, items: Vec<String> },
+    Paragraph(String),
+}
+
+/// Compiles `source` into the elements it describes (`h1`..`h6`, `p`,
+/// `ul`/`ol`/`li`, `pre`/`code`), attributing every generated node to `span`
+/// since the source is a single opaque string literal in the host grammar.
+pub(crate) fn compile(source: &str, span: Span) -> Vec<TreeNode> {
+    parse_blocks(source)
+        .into_iter()
+        .map(|block| block_to_node(block, span))
+        .collect()
+}
+
+fn parse_blocks(source: &str) -> Vec<Block> {
+    let mut blocks = vec![];
+    let mut lines = source.lines().peekable();
+
+    while let Some(line) = lines.peek().copied() {
+        let trimmed = line.trim_start();
+
+        if trimmed.is_empty() {
+            lines.next();
+            continue;
+        }
+
+        if trimmed.starts_with("```") {
+            lines.next();
+
+            let mut code = vec![];
+            for line in lines.by_ref() {
+                if line.trim_start().starts_with("```") {
+                    break;
+                }
+                code.push(line);
+            }
+
+            blocks.push(Block::Code(code.join("\n")));
+            continue;
+        }
+
+        if let Some((level, text)) = heading(trimmed) {
+            lines.next();
+            blocks.push(Block::Heading(level, text));
+            continue;
+        }
+
+        if is_list_item(trimmed) {
+            let ordered = trimmed.chars().next().is_some_and(|char| char.is_ascii_digit());
+            let mut items = vec![];
+
+            while let Some(line) = lines.peek().copied() {
+                let trimmed = line.trim_start();
+                if !is_list_item(trimmed) {
+                    break;
+                }
+
+                items.push(strip_list_marker(trimmed));
+                lines.next();
+            }
+
+            blocks.push(Block::List { ordered, items });
+            continue;
+        }
+
+        let mut paragraph = vec![];
+        while let Some(line) = lines.peek().copied() {
+            let trimmed = line.trim_start();
+            if trimmed.is_empty() || trimmed.starts_with("```") || heading(trimmed).is_some() || is_list_item(trimmed)
+            {
+                break;
+            }
+
+            paragraph.push(trimmed);
+            lines.next();
+        }
+
+        blocks.push(Block::Paragraph(paragraph.join(" ")));
+    }
+
+    blocks
+}
+
+/// An ATX heading (`#` through `######`, followed by a space).
+fn heading(line: &str) -> Option<(u8, String)> {
+    let level = line.chars().take_while(|&char| char == '#').count();
+    if level == 0 || level > 6 {
+        return None;
+    }
+
+    let rest = &line[level..];
+    rest.starts_with(' ').then(|| (level as u8, rest.trim().to_string()))
+}
+
+fn is_list_item(line: &str) -> bool {
+    if line.starts_with("- ") || line.starts_with("* ") {
+        return true;
+    }
+
+    let digits: String = line.chars().take_while(|char| char.is_ascii_digit()).collect();
+    !digits.is_empty() && line[digits.len()..].starts_with(". ")
+}
+
+fn strip_list_marker(line: &str) -> String {
+    if let Some(rest) = line.strip_prefix("- ").or_else(|| line.strip_prefix("* ")) {
+        return rest.to_string();
+    }
+
+    let digits: String = line.chars().take_while(|char| char.is_ascii_digit()).collect();
+    line[digits.len() + 2..].to_string()
+}
+
+fn block_to_node(block: Block, span: Span) -> TreeNode {
+    match block {
+        Block::Heading(level, text) => element(format!("h{level}"), parse_inline(&text, span), span),
+        Block::Paragraph(text) => element("p".to_string(), parse_inline(&text, span), span),
+        Block::Code(code) => element(
+            "pre".to_string(),
+            vec![Child::Element(element(
+                "code".to_string(),
+                vec![Child::Text(code)],
+                span,
+            ))],
+            span,
+        ),
+        Block::List { ordered, items } => element(
+            if ordered { "ol".to_string() } else { "ul".to_string() },
+            items
+                .into_iter()
+                .map(|item| Child::Element(element("li".to_string(), parse_inline(&item, span), span)))
+                .collect(),
+            span,
+        ),
+    }
+}
+
+/// Scans `text` for `**strong**`, `*em*`, `` `code` `` and `[text](url)`
+/// links, turning the runs in between into plain text children.
+fn parse_inline(text: &str, span: Span) -> Vec<Child> {
+    let mut children = vec![];
+    let mut buffer = String::new();
+    let mut rest = text;
+
+    while !rest.is_empty() {
+        if let Some(tail) = rest.strip_prefix('[') {
+            if let Some((label, url, after)) = link(tail) {
+                flush(&mut buffer, &mut children);
+                children.push(Child::Element(element_with_attrs(
+                    "a".to_string(),
+                    HashMap::from([("href".to_string(), AttributeValue::Literal(url))]),
+                    parse_inline(&label, span),
+                    span,
+                )));
+                rest = after;
+                continue;
+            }
+        }
+
+        if let Some((inner, after)) = rest.strip_prefix("**").and_then(|tail| delimited(tail, "**")) {
+            flush(&mut buffer, &mut children);
+            children.push(Child::Element(element("strong".to_string(), parse_inline(&inner, span), span)));
+            rest = after;
+            continue;
+        }
+
+        if let Some((inner, after)) = rest.strip_prefix('`').and_then(|tail| delimited(tail, "`")) {
+            flush(&mut buffer, &mut children);
+            children.push(Child::Element(element(
+                "code".to_string(),
+                vec![Child::Text(inner)],
+                span,
+            )));
+            rest = after;
+            continue;
+        }
+
+        if let Some((inner, after)) = rest.strip_prefix('*').and_then(|tail| delimited(tail, "*")) {
+            flush(&mut buffer, &mut children);
+            children.push(Child::Element(element("em".to_string(), parse_inline(&inner, span), span)));
+            rest = after;
+            continue;
+        }
+
+        let mut chars = rest.chars();
+        buffer.push(chars.next().expect("rest is non-empty"));
+        rest = chars.as_str();
+    }
+
+    flush(&mut buffer, &mut children);
+    children
+}
+
+/// Splits off the text up to the next occurrence of `delim`, if there is one
+/// and it isn't immediately adjacent to the opening delimiter (an empty span
+/// like the second `*` of an unclosed `**...` is treated as literal text
+/// instead of an empty emphasis/code span).
+fn delimited<'a>(text: &'a str, delim: &str) -> Option<(String, &'a str)> {
+    let end = text.find(delim)?;
+    if end == 0 {
+        return None;
+    }
+
+    Some((text[..end].to_string(), &text[end + delim.len()..]))
+}
+
+fn link(text: &str) -> Option<(String, String, &str)> {
+    let label_end = text.find(']')?;
+    let after_label = text[label_end + 1..].strip_prefix('(')?;
+    let url_end = after_label.find(')')?;
+
+    Some((
+        text[..label_end].to_string(),
+        after_label[..url_end].to_string(),
+        &after_label[url_end + 1..],
+    ))
+}
+
+fn flush(buffer: &mut String, children: &mut Vec<Child>) {
+    if !buffer.is_empty() {
+        children.push(Child::Text(std::mem::take(buffer)));
+    }
+}
+
+fn element(name: String, children: Vec<Child>, span: Span) -> TreeNode {
+    element_with_attrs(name, HashMap::new(), children, span)
+}
+
+fn element_with_attrs(
+    name: String,
+    attributes: HashMap<String, AttributeValue>,
+    children: Vec<Child>,
+    span: Span,
+) -> TreeNode {
+    TreeNode {
+        element: name,
+        attributes,
+        children,
+        span,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn span() -> Span {
+        Span {
+            offset: 0,
+            line: 1,
+            column: 1,
+        }
+    }
+
+    #[test]
+    fn parses_atx_heading() {
+        let blocks = parse_blocks("# Title");
+
+        assert!(matches!(blocks.as_slice(), [Block::Heading(1, text)] if text == "Title"));
+    }
+
+    #[test]
+    fn parses_fenced_code_block() {
+        let blocks = parse_blocks("```\nlet x = 1;\n```");
+
+        assert!(matches!(blocks.as_slice(), [Block::Code(code)] if code == "let x = 1;"));
+    }
+
+    #[test]
+    fn parses_unordered_and_ordered_lists() {
+        let unordered = parse_blocks("- one\n- two");
+        assert!(
+            matches!(unordered.as_slice(), [Block::List { ordered: false, items }] if items == &["one".to_string(), "two".to_string()])
+        );
+
+        let ordered = parse_blocks("1. one\n2. two");
+        assert!(matches!(ordered.as_slice(), [Block::List { ordered: true, .. }]));
+    }
+
+    #[test]
+    fn parses_paragraph_spanning_multiple_lines() {
+        let blocks = parse_blocks("one\ntwo");
+
+        assert!(matches!(blocks.as_slice(), [Block::Paragraph(text)] if text == "one two"));
+    }
+
+    #[test]
+    fn inline_emphasis_strong_code_and_links_in_order() {
+        let children = parse_inline("a *em* b **strong** c `code` d [text](url)", span());
+
+        let kinds: Vec<&str> = children
+            .iter()
+            .map(|child| match child {
+                Child::Text(_) => "text",
+                Child::Element(node) => node.element.as_str(),
+                Child::Expr(_, _) => "expr",
+            })
+            .collect();
+
+        assert_eq!(kinds, ["text", "em", "text", "strong", "text", "code", "text", "a"]);
+    }
+
+    #[test]
+    fn unterminated_emphasis_markers_are_treated_as_literal_text() {
+        let children = parse_inline("**unterminated strong", span());
+
+        assert!(matches!(children.as_slice(), [Child::Text(text)] if text == "**unterminated strong"));
+    }
+
+    #[test]
+    fn link_produces_anchor_with_href_and_inline_label() {
+        let children = parse_inline("[a *b*](/c)", span());
+
+        let [Child::Element(anchor)] = children.as_slice() else {
+            panic!("expected a single anchor element, got {children:?}")
+        };
+
+        assert_eq!(anchor.element, "a");
+        assert!(matches!(anchor.attributes.get("href"), Some(AttributeValue::Literal(url)) if url == "/c"));
+    }
+
+    #[test]
+    fn compile_turns_a_heading_and_paragraph_into_tree_nodes() {
+        let nodes = compile("# Title\n\nSome *text*.", span());
+
+        assert_eq!(nodes.len(), 2);
+        assert_eq!(nodes[0].element, "h1");
+        assert_eq!(nodes[1].element, "p");
+    }
+}
@@ -1,96 +1,429 @@
-use std::{collections::HashMap, iter::Peekable, slice::Iter};
+use std::{collections::HashMap, fmt, iter::Peekable, slice::Iter};
+
+use crate::markdown;
+use crate::schema::{self, Schema, ValidationError};
+
+/// A location in the source text, tracked both as a byte offset (for slicing)
+/// and as a 1-indexed line/column (for error messages).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Span {
+    pub offset: usize,
+    pub line: usize,
+    pub column: usize,
+}
+
+/// Tracks the current position while walking the source text, advancing one
+/// character at a time.
+#[derive(Clone, Copy)]
+struct Position {
+    offset: usize,
+    line: usize,
+    column: usize,
+}
+
+impl Position {
+    fn start() -> Position {
+        Position {
+            offset: 0,
+            line: 1,
+            column: 1,
+        }
+    }
+
+    fn span(&self) -> Span {
+        Span {
+            offset: self.offset,
+            line: self.line,
+            column: self.column,
+        }
+    }
+
+    fn advance(&mut self, char: char) {
+        self.offset += char.len_utf8();
+
+        if char == '\n' {
+            self.line += 1;
+            self.column = 1;
+        } else {
+            self.column += 1;
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum LexError {
+    UnexpectedCharacter { char: char, span: Span },
+    UnclosedStringLiteral { span: Span },
+    UnclosedExprBlock { span: Span },
+}
+
+impl LexError {
+    fn span(&self) -> Span {
+        match self {
+            LexError::UnexpectedCharacter { span, .. } => *span,
+            LexError::UnclosedStringLiteral { span } => *span,
+            LexError::UnclosedExprBlock { span } => *span,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum ParseError {
+    UnexpectedEof { span: Span },
+    TokenInInvalidPosition { span: Span },
+    AttributeValueExpected { span: Span },
+    VoidElementWithChildren { element: String, span: Span },
+    RuntimeExprUnsupported { span: Span },
+    MarkdownTextExpected { span: Span },
+}
+
+impl ParseError {
+    fn span(&self) -> Span {
+        match self {
+            ParseError::UnexpectedEof { span } => *span,
+            ParseError::TokenInInvalidPosition { span } => *span,
+            ParseError::AttributeValueExpected { span } => *span,
+            ParseError::VoidElementWithChildren { span, .. } => *span,
+            ParseError::RuntimeExprUnsupported { span } => *span,
+            ParseError::MarkdownTextExpected { span } => *span,
+        }
+    }
+}
+
+#[derive(Debug)]
+enum MarkupErrorKind {
+    Lex(LexError),
+    Parse(ParseError),
+    Schema(Vec<ValidationError>),
+}
+
+impl MarkupErrorKind {
+    fn span(&self) -> Span {
+        match self {
+            MarkupErrorKind::Lex(err) => err.span(),
+            MarkupErrorKind::Parse(err) => err.span(),
+            MarkupErrorKind::Schema(errors) => errors[0].span(),
+        }
+    }
+
+    fn message(&self) -> String {
+        match self {
+            MarkupErrorKind::Lex(LexError::UnexpectedCharacter { char, .. }) => {
+                format!("unexpected character '{char}'")
+            }
+            MarkupErrorKind::Lex(LexError::UnclosedStringLiteral { .. }) => {
+                "unclosed string literal".to_string()
+            }
+            MarkupErrorKind::Lex(LexError::UnclosedExprBlock { .. }) => {
+                "unclosed expression block".to_string()
+            }
+            MarkupErrorKind::Parse(ParseError::UnexpectedEof { .. }) => {
+                "unexpected end of input".to_string()
+            }
+            MarkupErrorKind::Parse(ParseError::TokenInInvalidPosition { .. }) => {
+                "token in invalid position".to_string()
+            }
+            MarkupErrorKind::Parse(ParseError::AttributeValueExpected { .. }) => {
+                "expected an attribute value".to_string()
+            }
+            MarkupErrorKind::Parse(ParseError::VoidElementWithChildren { element, .. }) => {
+                format!("<{element}> is a void element and cannot have children")
+            }
+            MarkupErrorKind::Parse(ParseError::RuntimeExprUnsupported { .. }) => {
+                "expression blocks can only be evaluated by the `markup!` macro, not the runtime compiler".to_string()
+            }
+            MarkupErrorKind::Parse(ParseError::MarkdownTextExpected { .. }) => {
+                "expected a string literal of Markdown text after `md`".to_string()
+            }
+            MarkupErrorKind::Schema(errors) => errors
+                .iter()
+                .map(ValidationError::message)
+                .collect::<Vec<_>>()
+                .join("; "),
+        }
+    }
+}
+
+/// An error produced while compiling a markup template, with enough context
+/// to point back at the offending source location.
+#[derive(Debug)]
+pub struct MarkupError {
+    source: String,
+    kind: MarkupErrorKind,
+}
+
+impl fmt::Display for MarkupError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let span = self.kind.span();
+        let line = self.source.lines().nth(span.line - 1).unwrap_or("");
+
+        writeln!(f, "error: {}", self.kind.message())?;
+        writeln!(f, "  --> line {}, column {}", span.line, span.column)?;
+        writeln!(f, "{line}")?;
+        write!(f, "{}^", " ".repeat(span.column.saturating_sub(1)))
+    }
+}
+
+impl std::error::Error for MarkupError {}
 
 #[derive(Debug)]
-enum Token {
+enum TokenKind {
     OpenParen,
     CloseParen,
     OpenBracket,
     CloseBracket,
     StringLiteral(String),
+    /// A `{ ... }` appearing where a value or content is expected (as
+    /// opposed to an element's children block) captures its raw, untokenized
+    /// contents to be spliced in by the `markup!` macro as a Rust expression.
+    ExprBlock(String),
     Identifier(String),
     Equals,
 }
 
+#[derive(Debug)]
+struct Token {
+    kind: TokenKind,
+    span: Span,
+}
+
 enum IncompleteToken {
     None,
-    StringLiteral(String),
-    Identifier(String),
+    StringLiteral(String, Span),
+    Identifier(String, Span),
 }
 
-fn tokenize(text: &str) -> Vec<Token> {
-    let mut tokens = vec![];
-    let mut state = IncompleteToken::None;
+/// A `{` starts an element's children block only when it directly follows
+/// the element's name or its closing `)` of attributes; anywhere else (after
+/// `=`, at the start of a children list, after another child) it opens an
+/// expression block instead.
+fn brace_opens_children_block(tokens: &[Token]) -> bool {
+    matches!(
+        tokens.last().map(|token| &token.kind),
+        Some(TokenKind::Identifier(_)) | Some(TokenKind::CloseParen)
+    )
+}
 
-    for char in text.chars() {
-        match state {
-            IncompleteToken::StringLiteral(ref mut string) => {
-                if char == '"' {
-                    tokens.push(Token::StringLiteral(string.to_string()));
-                    state = IncompleteToken::None;
-                } else {
-                    string.push(char);
+/// Consumes characters up to (and including) the matching `}`, tracking
+/// nested braces, and returns the raw text in between unparsed so it can be
+/// spliced in as a Rust expression by the `markup!` macro.
+fn scan_expr_block(
+    chars: &mut Peekable<std::str::Chars>,
+    pos: &mut Position,
+    start: Span,
+) -> Result<String, LexError> {
+    let mut depth = 1;
+    let mut raw = String::new();
+
+    loop {
+        let char = chars
+            .next()
+            .ok_or(LexError::UnclosedExprBlock { span: start })?;
+        pos.advance(char);
+
+        match char {
+            '{' => {
+                depth += 1;
+                raw.push(char);
+            }
+            '}' => {
+                depth -= 1;
+                if depth == 0 {
+                    break;
                 }
+                raw.push(char);
+            }
+            _ => raw.push(char),
+        }
+    }
 
-                continue;
+    Ok(raw.trim().to_string())
+}
+
+fn tokenize(text: &str) -> Result<Vec<Token>, LexError> {
+    let mut tokens = vec![];
+    let mut state = IncompleteToken::None;
+    let mut pos = Position::start();
+    let mut chars = text.chars().peekable();
+
+    while let Some(char) = chars.next() {
+        let char_span = pos.span();
+
+        if let IncompleteToken::StringLiteral(ref mut string, _) = state {
+            if char == '"' {
+                match std::mem::replace(&mut state, IncompleteToken::None) {
+                    IncompleteToken::StringLiteral(string, span) => {
+                        tokens.push(Token {
+                            kind: TokenKind::StringLiteral(string),
+                            span,
+                        });
+                    }
+                    _ => unreachable!(),
+                }
+            } else {
+                string.push(char);
             }
-            _ => {}
+
+            pos.advance(char);
+            continue;
         }
 
         match char {
             char if char.is_alphabetic() => match state {
                 IncompleteToken::None => {
-                    state = IncompleteToken::Identifier(char.to_string());
+                    state = IncompleteToken::Identifier(char.to_string(), char_span);
                 }
-                IncompleteToken::Identifier(ref mut string) => {
+                IncompleteToken::Identifier(ref mut string, _) => {
                     string.push(char);
                 }
-                _ => panic!(),
+                _ => unreachable!(),
             },
-            ' ' | '\n' | '{' | '}' | '(' | ')' | '=' => {
-                match state {
-                    IncompleteToken::Identifier(identifier) => {
-                        tokens.push(Token::Identifier(identifier));
-                        state = IncompleteToken::None;
+            '{' => {
+                match std::mem::replace(&mut state, IncompleteToken::None) {
+                    IncompleteToken::Identifier(identifier, span) => {
+                        tokens.push(Token {
+                            kind: TokenKind::Identifier(identifier),
+                            span,
+                        });
                     }
                     IncompleteToken::None => {}
-                    _ => panic!(),
+                    _ => unreachable!(),
+                }
+
+                if brace_opens_children_block(&tokens) {
+                    tokens.push(Token {
+                        kind: TokenKind::OpenBracket,
+                        span: char_span,
+                    });
+                    pos.advance(char);
+                } else {
+                    pos.advance(char);
+                    let raw = scan_expr_block(&mut chars, &mut pos, char_span)?;
+                    tokens.push(Token {
+                        kind: TokenKind::ExprBlock(raw),
+                        span: char_span,
+                    });
+                }
+
+                continue;
+            }
+            ' ' | '\n' | '}' | '(' | ')' | '=' => {
+                match std::mem::replace(&mut state, IncompleteToken::None) {
+                    IncompleteToken::Identifier(identifier, span) => {
+                        tokens.push(Token {
+                            kind: TokenKind::Identifier(identifier),
+                            span,
+                        });
+                    }
+                    IncompleteToken::None => {}
+                    _ => unreachable!(),
                 }
 
                 match char {
-                    '{' => tokens.push(Token::OpenBracket),
-                    '}' => tokens.push(Token::CloseBracket),
-                    '(' => tokens.push(Token::OpenParen),
-                    ')' => tokens.push(Token::CloseParen),
-                    '=' => tokens.push(Token::Equals),
+                    '}' => tokens.push(Token {
+                        kind: TokenKind::CloseBracket,
+                        span: char_span,
+                    }),
+                    '(' => tokens.push(Token {
+                        kind: TokenKind::OpenParen,
+                        span: char_span,
+                    }),
+                    ')' => tokens.push(Token {
+                        kind: TokenKind::CloseParen,
+                        span: char_span,
+                    }),
+                    '=' => tokens.push(Token {
+                        kind: TokenKind::Equals,
+                        span: char_span,
+                    }),
                     _ => {}
                 }
             }
-            '"' => state = IncompleteToken::StringLiteral(String::new()),
-            _ => panic!("Unknown character '{char}'"),
+            '"' => state = IncompleteToken::StringLiteral(String::new(), char_span),
+            _ => {
+                return Err(LexError::UnexpectedCharacter {
+                    char,
+                    span: char_span,
+                })
+            }
         }
+
+        pos.advance(char);
     }
 
-    return tokens;
+    match state {
+        IncompleteToken::StringLiteral(_, span) => return Err(LexError::UnclosedStringLiteral { span }),
+        IncompleteToken::Identifier(identifier, span) => tokens.push(Token {
+            kind: TokenKind::Identifier(identifier),
+            span,
+        }),
+        IncompleteToken::None => {}
+    }
+
+    Ok(tokens)
+}
+
+/// Elements that are always empty and must not be closed with a separate
+/// closing tag (e.g. `<br>` rather than `<br></br>`).
+fn is_void_element(element: &str) -> bool {
+    matches!(
+        element,
+        "area"
+            | "base"
+            | "br"
+            | "col"
+            | "embed"
+            | "hr"
+            | "img"
+            | "input"
+            | "link"
+            | "meta"
+            | "source"
+            | "track"
+            | "wbr"
+    )
+}
+
+/// An attribute's value: either a literal string or a captured Rust
+/// expression awaiting evaluation by the `markup!` macro.
+#[derive(Debug)]
+pub enum AttributeValue {
+    Literal(String),
+    Expr(String, Span),
+}
+
+/// A single entry in an element's children list.
+#[derive(Debug)]
+pub enum Child {
+    Element(TreeNode),
+    Text(String),
+    Expr(String, Span),
 }
 
 #[derive(Debug)]
 pub struct TreeNode {
-    element: String,
-    attributes: HashMap<String, String>,
-    children: Vec<TreeNode>,
+    pub(crate) element: String,
+    pub(crate) attributes: HashMap<String, AttributeValue>,
+    pub(crate) children: Vec<Child>,
+    pub(crate) span: Span,
 }
 
 impl TreeNode {
-    fn new(element: String) -> TreeNode {
+    fn new(element: String, span: Span) -> TreeNode {
         TreeNode {
             element,
             attributes: HashMap::new(),
             children: vec![],
+            span,
         }
     }
 }
 
-fn parse_attributes(tree: &mut TreeNode, iter: &mut Peekable<Iter<Token>>) {
+fn parse_attributes(
+    tree: &mut TreeNode,
+    iter: &mut Peekable<Iter<Token>>,
+    eof_span: Span,
+) -> Result<(), ParseError> {
     enum State {
         None,
         Attribute(String),
@@ -99,112 +432,472 @@ fn parse_attributes(tree: &mut TreeNode, iter: &mut Peekable<Iter<Token>>) {
 
     let mut state = State::None;
 
-    while let Some(token) = iter.next() {
-        match (state, token) {
-            (State::None, Token::Identifier(identifier)) => {
+    for token in iter.by_ref() {
+        match (state, &token.kind) {
+            (State::None, TokenKind::Identifier(identifier)) => {
                 state = State::Attribute(identifier.clone())
             }
-            (State::Attribute(attribute), Token::Equals) => {
+            (State::Attribute(attribute), TokenKind::Equals) => {
                 state = State::Equals(attribute.clone())
             }
-            (State::Equals(attribute), Token::StringLiteral(value)) => {
-                tree.attributes.insert(attribute, value.to_string());
+            (State::Equals(attribute), TokenKind::StringLiteral(value)) => {
+                tree.attributes
+                    .insert(attribute, AttributeValue::Literal(value.to_string()));
 
                 state = State::None;
             }
-            (State::None, Token::CloseParen) => {
-                return;
+            (State::Equals(attribute), TokenKind::ExprBlock(expr)) => {
+                tree.attributes
+                    .insert(attribute, AttributeValue::Expr(expr.to_string(), token.span));
+
+                state = State::None;
+            }
+            (State::None, TokenKind::CloseParen) => {
+                return Ok(());
             }
-            _ => panic!(),
+            (State::Equals(_), _) => {
+                return Err(ParseError::AttributeValueExpected { span: token.span });
+            }
+            _ => return Err(ParseError::TokenInInvalidPosition { span: token.span }),
         }
     }
+
+    Err(ParseError::UnexpectedEof { span: eof_span })
+}
+
+/// Whether `kind` is the `md` marker, which stands in content position for a
+/// string literal of Markdown text, rather than the start of an element.
+fn is_markdown_marker(kind: &TokenKind) -> bool {
+    matches!(kind, TokenKind::Identifier(identifier) if identifier == "md")
+}
+
+/// Parses the string literal following an `md` marker and compiles it into
+/// the elements it describes (see the `markdown` module).
+fn parse_markdown_block(
+    iter: &mut Peekable<Iter<Token>>,
+    eof_span: Span,
+) -> Result<Vec<TreeNode>, ParseError> {
+    let token = iter
+        .next()
+        .ok_or(ParseError::UnexpectedEof { span: eof_span })?;
+
+    match &token.kind {
+        TokenKind::StringLiteral(text) => Ok(markdown::compile(text, token.span)),
+        _ => Err(ParseError::MarkdownTextExpected { span: token.span }),
+    }
 }
 
-fn parse_elements(iter: &mut Peekable<Iter<Token>>) -> Vec<TreeNode> {
+fn parse_elements(
+    iter: &mut Peekable<Iter<Token>>,
+    eof_span: Span,
+) -> Result<Vec<TreeNode>, ParseError> {
     let mut elements = vec![];
 
-    while let Some(_) = iter.peek() {
-        elements.push(parse_element(iter));
+    while let Some(token) = iter.peek() {
+        if is_markdown_marker(&token.kind) {
+            iter.next();
+            elements.extend(parse_markdown_block(iter, eof_span)?);
+        } else {
+            elements.push(parse_element(iter, eof_span)?);
+        }
     }
 
-    return elements;
+    Ok(elements)
 }
 
-fn parse_element(mut iter: &mut Peekable<Iter<Token>>) -> TreeNode {
-    let element = match iter.next().unwrap() {
-        Token::Identifier(identifier) => identifier.to_string(),
-        _ => panic!("Cannot have unnamed elements"),
+fn parse_element(
+    iter: &mut Peekable<Iter<Token>>,
+    eof_span: Span,
+) -> Result<TreeNode, ParseError> {
+    let token = iter
+        .next()
+        .ok_or(ParseError::UnexpectedEof { span: eof_span })?;
+
+    let element_span = token.span;
+    let element = match &token.kind {
+        TokenKind::Identifier(identifier) => identifier.to_string(),
+        _ => return Err(ParseError::TokenInInvalidPosition { span: token.span }),
     };
 
-    let mut tree = TreeNode::new(element);
+    let mut tree = TreeNode::new(element, element_span);
 
     while let Some(token) = iter.peek() {
-        match token {
-            Token::OpenParen => {
+        match &token.kind {
+            TokenKind::OpenParen => {
                 iter.next();
-                parse_attributes(&mut tree, iter);
+                parse_attributes(&mut tree, iter, eof_span)?;
             }
-            Token::OpenBracket => {
+            TokenKind::OpenBracket => {
+                if is_void_element(&tree.element) {
+                    return Err(ParseError::VoidElementWithChildren {
+                        element: tree.element.clone(),
+                        span: token.span,
+                    });
+                }
+
                 iter.next();
-                tree.children = parse_elements(&mut iter);
+                tree.children = parse_children(iter, eof_span)?;
+
+                return Ok(tree);
             }
-            Token::CloseBracket => {
+            TokenKind::StringLiteral(text) => {
+                if is_void_element(&tree.element) {
+                    return Err(ParseError::VoidElementWithChildren {
+                        element: tree.element.clone(),
+                        span: token.span,
+                    });
+                }
+
+                // Shorthand form: `p "Hello"` is `p` with a single inline
+                // text child, with no surrounding `{ ... }` block.
+                tree.children.push(Child::Text(text.clone()));
                 iter.next();
-                iter.next(); // skip to token after bracket
-                return tree;
+
+                return Ok(tree);
             }
-            Token::Identifier(_) => {
-                return tree;
+            TokenKind::CloseBracket | TokenKind::Identifier(_) => {
+                return Ok(tree);
             }
-            _ => panic!("Token in invalid position"),
+            _ => return Err(ParseError::TokenInInvalidPosition { span: token.span }),
         };
     }
 
-    return tree;
+    Ok(tree)
 }
 
-fn parse(tokens: &[Token]) -> Vec<TreeNode> {
-    parse_elements(&mut tokens.iter().peekable())
+/// Parses the contents of an element's `{ ... }` children block, which may
+/// freely mix nested elements, inline text, and expression blocks, stopping
+/// once the closing `}` is consumed.
+fn parse_children(
+    iter: &mut Peekable<Iter<Token>>,
+    eof_span: Span,
+) -> Result<Vec<Child>, ParseError> {
+    let mut children = vec![];
+
+    loop {
+        let token = iter
+            .peek()
+            .ok_or(ParseError::UnexpectedEof { span: eof_span })?;
+
+        match &token.kind {
+            TokenKind::CloseBracket => {
+                iter.next();
+                break;
+            }
+            TokenKind::StringLiteral(text) => {
+                children.push(Child::Text(text.clone()));
+                iter.next();
+            }
+            TokenKind::ExprBlock(expr) => {
+                children.push(Child::Expr(expr.clone(), token.span));
+                iter.next();
+            }
+            TokenKind::Identifier(identifier) if identifier == "md" => {
+                iter.next();
+                children.extend(parse_markdown_block(iter, eof_span)?.into_iter().map(Child::Element));
+            }
+            TokenKind::Identifier(_) => {
+                children.push(Child::Element(parse_element(iter, eof_span)?));
+            }
+            _ => return Err(ParseError::TokenInInvalidPosition { span: token.span }),
+        }
+    }
+
+    Ok(children)
+}
+
+fn parse(tokens: &[Token]) -> Result<Vec<TreeNode>, ParseError> {
+    let eof_span = tokens.last().map(|token| token.span).unwrap_or(Span {
+        offset: 0,
+        line: 1,
+        column: 1,
+    });
+
+    parse_elements(&mut tokens.iter().peekable(), eof_span)
 }
 
-fn treenodes_to_html(tree_nodes: Peekable<Iter<TreeNode>>, ident_level: usize) -> String {
+/// Where an `Expr` node's value comes from when rendering to HTML text.
+/// `markup_text_to_html` has no way to evaluate a Rust expression, so it
+/// rejects them; `markup_text_to_format` (used by the `markup!` macro)
+/// collects them instead, leaving a `{}` placeholder for `format!` to fill.
+enum ExprSink<'a> {
+    Reject,
+    Collect(&'a mut Vec<String>),
+}
+
+impl ExprSink<'_> {
+    fn resolve(&mut self, expr: &str, span: Span) -> Result<String, ParseError> {
+        match self {
+            ExprSink::Reject => Err(ParseError::RuntimeExprUnsupported { span }),
+            ExprSink::Collect(args) => {
+                args.push(expr.to_string());
+                Ok("{}".to_string())
+            }
+        }
+    }
+
+    /// Escapes a literal chunk of HTML (text content or an attribute value)
+    /// that is about to be spliced into the rendered output. In `Collect`
+    /// mode the rendered output is itself used as a `format!` format string,
+    /// so any `{`/`}` the literal happens to contain must be doubled to
+    /// survive as literal braces; in `Reject` mode the output is the final
+    /// HTML, so it's passed through unchanged.
+    fn literal(&self, text: &str) -> String {
+        match self {
+            ExprSink::Reject => text.to_string(),
+            ExprSink::Collect(_) => text.replace('{', "{{").replace('}', "}}"),
+        }
+    }
+}
+
+fn render_nodes(
+    tree_nodes: Peekable<Iter<TreeNode>>,
+    ident_level: usize,
+    sink: &mut ExprSink,
+) -> Result<String, ParseError> {
     let html = tree_nodes
-        .map(|node| treenode_to_html(node, ident_level))
-        .collect::<Vec<_>>()
+        .map(|node| render_node(node, ident_level, sink))
+        .collect::<Result<Vec<_>, _>>()?
+        .join("\n");
+
+    Ok(html)
+}
+
+fn render_children(
+    children: &[Child],
+    ident_level: usize,
+    sink: &mut ExprSink,
+) -> Result<String, ParseError> {
+    let ident = vec!["\t"; ident_level].join("");
+
+    let html = children
+        .iter()
+        .map(|child| match child {
+            Child::Element(node) => render_node(node, ident_level, sink),
+            Child::Text(text) => Ok(format!("{ident}{}", sink.literal(text))),
+            Child::Expr(expr, span) => Ok(format!("{ident}{}", sink.resolve(expr, *span)?)),
+        })
+        .collect::<Result<Vec<_>, _>>()?
         .join("\n");
 
-    return html;
+    Ok(html)
 }
 
-pub fn treenode_to_html(tree: &TreeNode, ident_level: usize) -> String {
+fn render_node(tree: &TreeNode, ident_level: usize, sink: &mut ExprSink) -> Result<String, ParseError> {
     let mut html = String::new();
 
     let mut attributes_html = String::new();
 
     for (attribute, value) in &tree.attributes {
-        attributes_html.push_str(&format!("{attribute}=\"{value}\""));
+        match value {
+            AttributeValue::Literal(value) => {
+                attributes_html.push_str(&format!("{attribute}=\"{}\"", sink.literal(value)));
+            }
+            AttributeValue::Expr(expr, span) => {
+                attributes_html.push_str(&format!("{attribute}=\"{}\"", sink.resolve(expr, *span)?));
+            }
+        }
     }
 
     let ident = vec!["\t"; ident_level].join("");
 
+    if is_void_element(&tree.element) {
+        if tree.attributes.is_empty() {
+            html.push_str(&format!("{ident}<{}>", tree.element))
+        } else {
+            html.push_str(&format!("{ident}<{} {attributes_html}>", tree.element))
+        }
+
+        return Ok(html);
+    }
+
     if tree.attributes.is_empty() {
         html.push_str(&format!("{ident}<{}>\n", tree.element))
     } else {
         html.push_str(&format!("{ident}<{} {attributes_html}>\n", tree.element))
     }
 
-    html.push_str(&treenodes_to_html(
-        tree.children.iter().peekable(),
-        ident_level + 1,
-    ));
+    html.push_str(&render_children(&tree.children, ident_level + 1, sink)?);
 
     html.push_str(&format!("\n{ident}</{}>", tree.element));
 
-    return html;
+    Ok(html)
+}
+
+/// Renders a single element, erroring if it (or any descendant) contains an
+/// expression block — those can only be evaluated by the `markup!` macro.
+pub fn treenode_to_html(tree: &TreeNode, ident_level: usize) -> Result<String, ParseError> {
+    render_node(tree, ident_level, &mut ExprSink::Reject)
+}
+
+/// Parses markup source into a tree, without serializing it. This is the
+/// point at which a schema validation pass (see the `schema` module) runs,
+/// before the tree is handed to `treenode_to_html`.
+pub fn parse_markup(text: &str) -> Result<Vec<TreeNode>, MarkupError> {
+    let to_error = |kind: MarkupErrorKind| MarkupError {
+        source: text.to_string(),
+        kind,
+    };
+
+    let tokens = tokenize(text).map_err(|err| to_error(MarkupErrorKind::Lex(err)))?;
+    let tree = parse(&tokens).map_err(|err| to_error(MarkupErrorKind::Parse(err)))?;
+
+    schema::validate(&tree, &Schema::default()).map_err(|errors| to_error(MarkupErrorKind::Schema(errors)))?;
+
+    Ok(tree)
+}
+
+pub fn markup_text_to_html(text: &str) -> Result<String, MarkupError> {
+    let to_error = |kind: MarkupErrorKind| MarkupError {
+        source: text.to_string(),
+        kind,
+    };
+
+    let tree = parse_markup(text)?;
+
+    render_nodes(tree.iter().peekable(), 0, &mut ExprSink::Reject)
+        .map_err(|err| to_error(MarkupErrorKind::Parse(err)))
 }
 
-pub fn markup_text_to_html(text: &str) -> String {
-    let tree = parse(&tokenize(text));
+/// Compiles the template into a `format!`-style string containing a `{}`
+/// placeholder for every expression block, plus the raw source of each
+/// expression in the order they appear. The `markup!` macro parses each
+/// entry back into a real expression and splices the whole thing into a
+/// generated `format!(...)` call.
+pub fn markup_text_to_format(text: &str) -> Result<(String, Vec<String>), MarkupError> {
+    let to_error = |kind: MarkupErrorKind| MarkupError {
+        source: text.to_string(),
+        kind,
+    };
 
-    treenodes_to_html(tree.iter().peekable(), 0)
+    let tree = parse_markup(text)?;
+
+    let mut args = vec![];
+    let html = render_nodes(tree.iter().peekable(), 0, &mut ExprSink::Collect(&mut args))
+        .map_err(|err| to_error(MarkupErrorKind::Parse(err)))?;
+
+    Ok((html, args))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tokenize_reports_unclosed_string_literal_at_the_opening_quote() {
+        let err = tokenize("p \"unterminated").unwrap_err();
+
+        assert!(matches!(
+            err,
+            LexError::UnclosedStringLiteral { span } if span.line == 1 && span.column == 3
+        ));
+    }
+
+    #[test]
+    fn tokenize_reports_unexpected_character_at_its_own_position() {
+        let err = tokenize("p #").unwrap_err();
+
+        assert!(matches!(
+            err,
+            LexError::UnexpectedCharacter { char: '#', span } if span.line == 1 && span.column == 3
+        ));
+    }
+
+    #[test]
+    fn parse_reports_unexpected_eof_inside_an_open_children_block() {
+        let tokens = tokenize("div {").unwrap();
+        let err = parse(&tokens).unwrap_err();
+
+        assert!(matches!(err, ParseError::UnexpectedEof { .. }));
+    }
+
+    #[test]
+    fn parse_reports_token_in_invalid_position() {
+        let tokens = tokenize("p (=\"x\")").unwrap();
+        let err = parse(&tokens).unwrap_err();
+
+        assert!(matches!(err, ParseError::TokenInInvalidPosition { .. }));
+    }
+
+    #[test]
+    fn void_element_serializes_without_a_closing_tag() {
+        let tree = TreeNode::new("br".to_string(), Span { offset: 0, line: 1, column: 1 });
+
+        assert_eq!(treenode_to_html(&tree, 0).unwrap(), "<br>");
+    }
+
+    #[test]
+    fn void_element_with_attributes_stays_self_closing() {
+        let mut tree = TreeNode::new("img".to_string(), Span { offset: 0, line: 1, column: 1 });
+        tree.attributes
+            .insert("src".to_string(), AttributeValue::Literal("cat.png".to_string()));
+
+        assert_eq!(treenode_to_html(&tree, 0).unwrap(), "<img src=\"cat.png\">");
+    }
+
+    #[test]
+    fn shorthand_text_child_on_a_void_element_is_rejected() {
+        let err = markup_text_to_html("div { br \"oops\" }").unwrap_err();
+
+        assert!(matches!(
+            &err.kind,
+            MarkupErrorKind::Parse(ParseError::VoidElementWithChildren { element, .. }) if element == "br"
+        ));
+    }
+
+    #[test]
+    fn children_block_on_a_void_element_is_rejected() {
+        let err = markup_text_to_html("div { br { p \"oops\" } }").unwrap_err();
+
+        assert!(matches!(
+            &err.kind,
+            MarkupErrorKind::Parse(ParseError::VoidElementWithChildren { element, .. }) if element == "br"
+        ));
+    }
+
+    #[test]
+    fn markup_text_to_html_rejects_expression_blocks() {
+        let err = markup_text_to_html("p { { name } }").unwrap_err();
+
+        assert!(matches!(
+            &err.kind,
+            MarkupErrorKind::Parse(ParseError::RuntimeExprUnsupported { .. })
+        ));
+    }
+
+    #[test]
+    fn markup_text_to_format_collects_expressions_as_format_args() {
+        let (html, args) = markup_text_to_format("p { { name } }").unwrap();
+
+        assert_eq!(html, "<p>\n\t{}\n</p>");
+        assert_eq!(args, vec!["name".to_string()]);
+    }
+
+    #[test]
+    fn markup_text_to_format_escapes_literal_braces_but_not_expr_placeholders() {
+        let (html, args) = markup_text_to_format("p { \"curly {brace}\" { name } }").unwrap();
+
+        assert_eq!(html, "<p>\n\tcurly {{brace}}\n\t{}\n</p>");
+        assert_eq!(args, vec!["name".to_string()]);
+    }
+
+    #[test]
+    fn markup_text_to_html_leaves_literal_braces_untouched() {
+        let html = markup_text_to_html("p \"curly {brace}\"").unwrap();
+
+        assert_eq!(html, "<p>\n\tcurly {brace}\n</p>");
+    }
+
+    #[test]
+    fn markup_error_display_points_a_caret_at_the_offending_column() {
+        let err = parse_markup("div { br \"oops\" }").unwrap_err();
+
+        assert_eq!(
+            err.to_string(),
+            "error: <br> is a void element and cannot have children\n\
+             \x20 --> line 1, column 10\n\
+             div { br \"oops\" }\n\
+             \x20        ^"
+        );
+    }
 }
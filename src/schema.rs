@@ -0,0 +1,268 @@
+//! Schema-driven validation for a parsed markup tree: required children and
+//! recognized attributes, run as a pass over the `Vec<TreeNode>` produced by
+//! the parser, before serialization.
+
+use std::collections::HashMap;
+
+use crate::markup::{Child, Span, TreeNode};
+
+/// Which child elements an element is required to contain, and which
+/// attributes are recognized, either globally or per element. Callers can
+/// build their own tables with [`Schema::new`] and pass them to [`validate`].
+/// The compilers in `crate::markup` (`parse_markup`, `markup_text_to_html`,
+/// `markup_text_to_format`) always validate against [`Schema::html5`] first,
+/// so a tree they return has already passed the built-in rules; a custom
+/// schema is checked on top of those rules and cannot let through what they
+/// reject.
+pub struct Schema {
+    required_children: HashMap<&'static str, &'static [&'static str]>,
+    global_attrs: &'static [&'static str],
+    element_attrs: HashMap<&'static str, &'static [&'static str]>,
+}
+
+impl Schema {
+    pub fn new(
+        required_children: HashMap<&'static str, &'static [&'static str]>,
+        global_attrs: &'static [&'static str],
+        element_attrs: HashMap<&'static str, &'static [&'static str]>,
+    ) -> Schema {
+        Schema {
+            required_children,
+            global_attrs,
+            element_attrs,
+        }
+    }
+
+    /// The built-in HTML5 ruleset: `html` requires `head`+`body`, `head`
+    /// requires `title`, and a handful of common elements get their
+    /// element-specific attributes recognized. `data-*`/`aria-*` attributes
+    /// are always allowed, for custom elements and web components.
+    pub fn html5() -> Schema {
+        let required_children = HashMap::from([
+            ("html", ["head", "body"].as_slice()),
+            ("head", ["title"].as_slice()),
+        ]);
+
+        let global_attrs: &'static [&'static str] = &["id", "class", "style", "title", "lang"];
+
+        let element_attrs = HashMap::from([
+            ("html", ["lang"].as_slice()),
+            ("a", ["href", "target", "rel"].as_slice()),
+            ("img", ["src", "alt", "width", "height"].as_slice()),
+            (
+                "input",
+                ["type", "name", "value", "placeholder", "checked", "disabled"].as_slice(),
+            ),
+            ("link", ["rel", "href", "type"].as_slice()),
+            ("meta", ["name", "content", "charset"].as_slice()),
+            ("script", ["src", "type", "async", "defer"].as_slice()),
+            ("label", ["for"].as_slice()),
+            ("button", ["type", "disabled"].as_slice()),
+            ("form", ["action", "method"].as_slice()),
+        ]);
+
+        Schema::new(required_children, global_attrs, element_attrs)
+    }
+}
+
+impl Default for Schema {
+    fn default() -> Schema {
+        Schema::html5()
+    }
+}
+
+#[derive(Debug)]
+pub enum ValidationError {
+    MissingRequiredChild {
+        element: String,
+        required: &'static str,
+        span: Span,
+    },
+    UnrecognizedAttribute {
+        element: String,
+        attribute: String,
+        span: Span,
+    },
+}
+
+impl ValidationError {
+    pub(crate) fn span(&self) -> Span {
+        match self {
+            ValidationError::MissingRequiredChild { span, .. } => *span,
+            ValidationError::UnrecognizedAttribute { span, .. } => *span,
+        }
+    }
+
+    pub(crate) fn message(&self) -> String {
+        match self {
+            ValidationError::MissingRequiredChild { element, required, .. } => {
+                format!("<{element}> is missing a required <{required}> child")
+            }
+            ValidationError::UnrecognizedAttribute { element, attribute, .. } => {
+                format!("`{attribute}` is not a recognized attribute on <{element}>")
+            }
+        }
+    }
+}
+
+/// Runs `schema`'s rules over every node in `tree_nodes`, collecting every
+/// violation found rather than stopping at the first one.
+pub fn validate(tree_nodes: &[TreeNode], schema: &Schema) -> Result<(), Vec<ValidationError>> {
+    let mut errors = vec![];
+
+    for node in tree_nodes {
+        validate_node(node, schema, &mut errors);
+    }
+
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(errors)
+    }
+}
+
+fn validate_node(node: &TreeNode, schema: &Schema, errors: &mut Vec<ValidationError>) {
+    if let Some(required) = schema.required_children.get(node.element.as_str()) {
+        for required in *required {
+            let has_child = node.children.iter().any(|child| match child {
+                Child::Element(child) => child.element == *required,
+                Child::Text(_) | Child::Expr(_, _) => false,
+            });
+
+            if !has_child {
+                errors.push(ValidationError::MissingRequiredChild {
+                    element: node.element.clone(),
+                    required,
+                    span: node.span,
+                });
+            }
+        }
+    }
+
+    for attribute in node.attributes.keys() {
+        if !is_attribute_recognized(attribute, &node.element, schema) {
+            errors.push(ValidationError::UnrecognizedAttribute {
+                element: node.element.clone(),
+                attribute: attribute.clone(),
+                span: node.span,
+            });
+        }
+    }
+
+    for child in &node.children {
+        if let Child::Element(child) = child {
+            validate_node(child, schema, errors);
+        }
+    }
+}
+
+fn is_attribute_recognized(attribute: &str, element: &str, schema: &Schema) -> bool {
+    if attribute.starts_with("data-") || attribute.starts_with("aria-") {
+        return true;
+    }
+
+    if schema.global_attrs.contains(&attribute) {
+        return true;
+    }
+
+    schema
+        .element_attrs
+        .get(element)
+        .is_some_and(|attrs| attrs.contains(&attribute))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::markup::AttributeValue;
+
+    fn span() -> Span {
+        Span {
+            offset: 0,
+            line: 1,
+            column: 1,
+        }
+    }
+
+    fn node(element: &str, attributes: &[(&str, &str)], children: Vec<Child>) -> TreeNode {
+        TreeNode {
+            element: element.to_string(),
+            attributes: attributes
+                .iter()
+                .map(|(name, value)| (name.to_string(), AttributeValue::Literal(value.to_string())))
+                .collect(),
+            children,
+            span: span(),
+        }
+    }
+
+    #[test]
+    fn html5_schema_requires_head_and_body() {
+        let html = node("html", &[], vec![Child::Element(node("body", &[], vec![]))]);
+
+        let errors = validate(&[html], &Schema::html5()).unwrap_err();
+
+        assert!(matches!(
+            errors.as_slice(),
+            [ValidationError::MissingRequiredChild { required: "head", .. }]
+        ));
+    }
+
+    #[test]
+    fn html5_schema_accepts_html_with_head_and_body() {
+        let html = node(
+            "html",
+            &[],
+            vec![
+                Child::Element(node("head", &[], vec![Child::Element(node("title", &[], vec![]))])),
+                Child::Element(node("body", &[], vec![])),
+            ],
+        );
+
+        assert!(validate(&[html], &Schema::html5()).is_ok());
+    }
+
+    #[test]
+    fn unrecognized_attribute_is_rejected() {
+        let div = node("div", &[("onclick", "doStuff()")], vec![]);
+
+        let errors = validate(&[div], &Schema::html5()).unwrap_err();
+
+        assert!(matches!(
+            errors.as_slice(),
+            [ValidationError::UnrecognizedAttribute { attribute, .. }] if attribute == "onclick"
+        ));
+    }
+
+    #[test]
+    fn global_and_data_attrs_are_always_recognized() {
+        let div = node("div", &[("class", "card"), ("data-id", "1"), ("aria-hidden", "true")], vec![]);
+
+        assert!(validate(&[div], &Schema::html5()).is_ok());
+    }
+
+    #[test]
+    fn element_specific_attrs_are_scoped_to_their_element() {
+        let a = node("a", &[("href", "/")], vec![]);
+        let div = node("div", &[("href", "/")], vec![]);
+
+        assert!(validate(&[a], &Schema::html5()).is_ok());
+        assert!(validate(&[div], &Schema::html5()).is_err());
+    }
+
+    #[test]
+    fn validation_descends_into_children() {
+        let tree = node(
+            "div",
+            &[],
+            vec![Child::Element(node("span", &[("onclick", "x")], vec![]))],
+        );
+
+        let errors = validate(&[tree], &Schema::html5()).unwrap_err();
+
+        assert!(matches!(
+            errors.as_slice(),
+            [ValidationError::UnrecognizedAttribute { element, .. }] if element == "span"
+        ));
+    }
+}